@@ -1,6 +1,6 @@
 use std::{
-    collections::HashMap,
-    net::{SocketAddr, UdpSocket},
+    collections::{HashMap, VecDeque},
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
     sync::mpsc,
     thread,
     time::{Duration, Instant},
@@ -24,38 +24,263 @@ pub struct JoyconStatus {
     pub design: JoyconDesign,
     pub mount_rotation: i32,
     pub serial_number: String,
+    /// True for the status tick in which the gyro bias was just re-zeroed
+    /// from an idle period, so the UI can flash a "recalibrated" indicator.
+    pub recalibrated: bool,
+    pub battery_percentage: f32,
+    pub battery_charging: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct JoyconDeviceInfo {
     pub serial_number: String,
     pub design: JoyconDesign,
+    pub calibration: Calibration,
+}
+
+/// Per-axis accelerometer/gyro calibration read from a Joy-Con's SPI flash.
+///
+/// The factory block is always present; a user calibration block, when the
+/// controller has one, takes precedence. Offsets/scales are derived once
+/// (see [`Calibration::from_spi_blocks`]) so applying calibration to a frame
+/// in the hot path is just a subtract and a multiply per axis.
+#[derive(Debug, Clone, Copy)]
+pub struct Calibration {
+    accel_origin: Vector3<f64>,
+    accel_scale: Vector3<f64>,
+    gyro_origin: Vector3<f64>,
+    gyro_scale: Vector3<f64>,
+}
+
+impl Calibration {
+    /// Accelerometer full-scale range, in g, used by the Joy-Con IMU.
+    const ACCEL_RANGE_G: f64 = 8.0;
+    /// Constant from the factory calibration spec for converting the gyro
+    /// sensitivity reading into degrees-per-second.
+    const GYRO_SENSITIVITY_CONST: f64 = 936.0;
+    /// Length, in bytes, of a single calibration block (factory or user).
+    const BLOCK_LEN: usize = 24;
+    /// Marker preceding a user calibration block when one has been written.
+    const USER_MAGIC: [u8; 2] = [0xB2, 0xA1];
+
+    /// No-op calibration, used before the real SPI blocks have been read.
+    pub fn identity() -> Self {
+        Calibration {
+            accel_origin: Vector3::zeros(),
+            accel_scale: Vector3::new(1.0, 1.0, 1.0),
+            gyro_origin: Vector3::zeros(),
+            gyro_scale: Vector3::new(1.0, 1.0, 1.0),
+        }
+    }
+
+    /// Builds a `Calibration` from the 24-byte factory block, preferring the
+    /// 26-byte (2-byte magic + 24-byte block) user block when it is present.
+    pub fn from_spi_blocks(factory: &[u8], user: Option<&[u8]>) -> Self {
+        let block = match user {
+            Some(bytes) if bytes.len() >= Self::BLOCK_LEN + 2 && bytes[0..2] == Self::USER_MAGIC => {
+                &bytes[2..2 + Self::BLOCK_LEN]
+            }
+            _ => &factory[..Self::BLOCK_LEN],
+        };
+
+        let accel_origin = read_i16_triplet(&block[0..6]);
+        let accel_sensitivity = read_i16_triplet(&block[6..12]);
+        let gyro_origin = read_i16_triplet(&block[12..18]);
+        let gyro_sensitivity = read_i16_triplet(&block[18..24]);
+
+        let accel_scale = (accel_sensitivity - accel_origin).map(|d| Self::ACCEL_RANGE_G / d);
+        let gyro_scale =
+            (gyro_sensitivity - gyro_origin).map(|d| Self::GYRO_SENSITIVITY_CONST / d);
+
+        Calibration {
+            accel_origin,
+            accel_scale,
+            gyro_origin,
+            gyro_scale,
+        }
+    }
+
+    /// Converts a raw axis frame into physically calibrated units.
+    fn apply(&self, raw: JoyconAxisData) -> JoyconAxisData {
+        let accel = (Vector3::new(raw.accel_x, raw.accel_y, raw.accel_z) - self.accel_origin)
+            .component_mul(&self.accel_scale);
+        let gyro = (Vector3::new(raw.gyro_x, raw.gyro_y, raw.gyro_z) - self.gyro_origin)
+            .component_mul(&self.gyro_scale);
+        JoyconAxisData {
+            accel_x: accel.x,
+            accel_y: accel.y,
+            accel_z: accel.z,
+            gyro_x: gyro.x,
+            gyro_y: gyro.y,
+            gyro_z: gyro.z,
+            ..raw
+        }
+    }
+}
+
+fn read_i16_triplet(bytes: &[u8]) -> Vector3<f64> {
+    Vector3::new(
+        i16::from_le_bytes([bytes[0], bytes[1]]) as f64,
+        i16::from_le_bytes([bytes[2], bytes[3]]) as f64,
+        i16::from_le_bytes([bytes[4], bytes[5]]) as f64,
+    )
+}
+
+/// How often a SlimeVR battery-level packet is re-sent per device.
+const BATTERY_SEND_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Subnet broadcast address used to probe for a SlimeVR server when no
+/// manual address is configured and mDNS discovery is enabled.
+fn discovery_broadcast_addr() -> SocketAddr {
+    SocketAddr::from((Ipv4Addr::BROADCAST, 6969))
 }
 
 struct Device {
     imu: Imu,
     design: JoyconDesign,
     id: u8,
+    calibration: Calibration,
+    /// Current zero-rate offset subtracted from incoming gyro readings.
+    gyro_bias: Vector3<f64>,
+    /// Recent (timestamp, gyro) samples used to detect the controller resting.
+    idle_window: VecDeque<(Instant, Vector3<f64>)>,
+    /// Set for one status tick right after `gyro_bias` is re-zeroed.
+    recalibrated: bool,
+    /// Last time a `ChannelInfo::Data` message arrived for this device.
+    last_seen: Instant,
+    /// True once `last_seen` has exceeded `settings.device_timeout`, until
+    /// data resumes and the device is re-handshaken.
+    stale: bool,
+    battery_percentage: f32,
+    battery_charging: bool,
+    /// Last time a SlimeVR battery-level packet was sent for this device.
+    last_battery_send: Instant,
 }
 
 impl Device {
-    pub fn handshake(&self, socket: &UdpSocket, address: &SocketAddr) {
+    fn send_sensor_status(&self, socket: &UdpSocket, address: &SocketAddr, sensor_status: u8) {
         let sensor_info = PacketType::SensorInfo {
             packet_id: 0,
             sensor_id: self.id,
-            sensor_status: 1,
+            sensor_status,
             sensor_type: 0,
         };
         socket
             .send_to(&sensor_info.to_bytes().unwrap(), address)
             .unwrap();
     }
+
+    pub fn handshake(&self, socket: &UdpSocket, address: &SocketAddr) {
+        self.send_sensor_status(socket, address, 1);
+    }
+
+    /// Feeds a fresh gyro reading into the idle window and, once the
+    /// controller has held still (low magnitude, low variance) for the
+    /// configured window length, re-zeros `gyro_bias` to the window's mean.
+    /// Returns true if this call found the window full and re-zeroed
+    /// `gyro_bias`. A `Data` message carries 3 sub-frames sharing one `now`,
+    /// so the caller must OR this across all of them rather than letting a
+    /// per-frame flag on `self` get reset by the next frame's call.
+    fn observe_gyro_idle(
+        &mut self,
+        gyro: Vector3<f64>,
+        now: Instant,
+        settings: &settings::WranglerSettings,
+    ) -> bool {
+        self.idle_window.push_back((now, gyro));
+
+        // Check fullness against the window's oldest sample before pruning
+        // evicts it; checking afterward means nothing left in the deque can
+        // ever be older than the window, so the check could never pass.
+        let window_full = self
+            .idle_window
+            .front()
+            .is_some_and(|&(oldest, _)| now.duration_since(oldest) >= settings.gyro_idle_window);
+
+        while let Some(&(oldest, _)) = self.idle_window.front() {
+            if now.duration_since(oldest) > settings.gyro_idle_window {
+                self.idle_window.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if !window_full {
+            return false;
+        }
+
+        let n = self.idle_window.len() as f64;
+        let mut mean = Vector3::zeros();
+        for (_, g) in &self.idle_window {
+            mean += g;
+        }
+        mean /= n;
+
+        let magnitude = mean.norm();
+        let variance = self
+            .idle_window
+            .iter()
+            .map(|(_, g)| (g - mean).norm_squared())
+            .sum::<f64>()
+            / n;
+
+        if magnitude <= settings.gyro_idle_magnitude_threshold_dps
+            && variance <= settings.gyro_idle_variance_threshold
+        {
+            self.gyro_bias = mean;
+            self.idle_window.clear();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// A rumble feedback pattern the HID-side `spawn_thread` can play back on a
+/// physical Joy-Con, encoded as the standard 8-byte frequency/amplitude pair.
+#[derive(Debug, Clone, Copy)]
+pub enum RumblePattern {
+    /// Short pulse played once a controller finishes handshake/assignment.
+    Handshake,
+    /// Distinct pattern played when idle gyro recalibration completes.
+    Recalibrated,
+    /// Buzz the user can trigger from the UI to find a physical controller.
+    Identify,
+}
+
+impl RumblePattern {
+    /// Encodes this pattern as the 8-byte frequency/amplitude payload the
+    /// Joy-Con's rumble HID report expects (left and right actuator halves).
+    pub fn to_hid_bytes(self) -> [u8; 8] {
+        let (freq_hb, freq_lb, amp_hb, amp_lb) = match self {
+            RumblePattern::Handshake => (0x00, 0x01, 0x40, 0x40),
+            RumblePattern::Recalibrated => (0x00, 0x01, 0x20, 0x20),
+            RumblePattern::Identify => (0x00, 0x01, 0x70, 0x70),
+        };
+        [
+            freq_hb, freq_lb, amp_hb, amp_lb, freq_hb, freq_lb, amp_hb, amp_lb,
+        ]
+    }
+}
+
+/// A command sent from `main_thread`/`JoyconIntegration` to the HID-side
+/// `spawn_thread`, which owns the actual device handle.
+#[derive(Debug, Clone)]
+pub enum JoyconCommand {
+    Rumble {
+        serial_number: String,
+        pattern: RumblePattern,
+    },
 }
 
 #[derive(Debug, Clone)]
 pub struct JoyconData {
     pub serial_number: String,
     pub imu_data: [JoyconAxisData; 3],
+    /// Charge level decoded from the Joy-Con input report, 0.0-1.0.
+    pub battery_percentage: f32,
+    /// Whether the controller is currently on its charging grip/dock.
+    pub battery_charging: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -71,17 +296,35 @@ fn serial_number_to_mac(serial: &str) -> [u8; 6] {
 }
 */
 
+fn send_rumble(command_tx: &mpsc::Sender<JoyconCommand>, serial_number: String, pattern: RumblePattern) {
+    let _drop = command_tx.send(JoyconCommand::Rumble {
+        serial_number,
+        pattern,
+    });
+}
+
 fn parse_message(
     msg: ChannelInfo,
     devices: &mut HashMap<String, Device>,
     socket: &UdpSocket,
     address: &SocketAddr,
     settings: &settings::WranglerSettings,
+    command_tx: &mpsc::Sender<JoyconCommand>,
 ) {
     match msg {
         ChannelInfo::Connected(device_info) => {
             if devices.contains_key(&device_info.serial_number) {
-                devices.get_mut(&device_info.serial_number).unwrap().imu = Imu::new();
+                let device = devices.get_mut(&device_info.serial_number).unwrap();
+                device.imu = Imu::new();
+                device.calibration = device_info.calibration;
+                device.gyro_bias = Vector3::zeros();
+                device.idle_window.clear();
+                device.recalibrated = false;
+                device.last_seen = Instant::now();
+                if device.stale {
+                    device.handshake(socket, address);
+                    device.stale = false;
+                }
                 return;
             }
             let id = devices.len() as _;
@@ -89,13 +332,56 @@ fn parse_message(
                 design: device_info.design,
                 imu: Imu::new(),
                 id,
+                calibration: device_info.calibration,
+                gyro_bias: Vector3::zeros(),
+                idle_window: VecDeque::new(),
+                recalibrated: false,
+                last_seen: Instant::now(),
+                stale: false,
+                battery_percentage: 1.0,
+                battery_charging: false,
+                last_battery_send: Instant::now() - BATTERY_SEND_INTERVAL,
             };
             device.handshake(socket, address);
+            send_rumble(command_tx, device_info.serial_number.clone(), RumblePattern::Handshake);
             devices.insert(device_info.serial_number, device);
         }
         ChannelInfo::Data(data) => {
             if let Some(device) = devices.get_mut(&data.serial_number) {
-                for frame in data.imu_data {
+                let now = Instant::now();
+                if device.stale {
+                    device.imu = Imu::new();
+                    device.handshake(socket, address);
+                    device.stale = false;
+                    send_rumble(command_tx, data.serial_number.clone(), RumblePattern::Handshake);
+                }
+                device.last_seen = now;
+                device.battery_percentage = data.battery_percentage;
+                device.battery_charging = data.battery_charging;
+
+                let calibrated_data = data.imu_data.map(|frame| device.calibration.apply(frame));
+                let mut recalibrated_this_message = false;
+                for frame in calibrated_data {
+                    recalibrated_this_message |=
+                        device.observe_gyro_idle(Vector3::new(frame.gyro_x, frame.gyro_y, frame.gyro_z), now, settings);
+                }
+                // Kept set until `main_thread` has sent the next status tick,
+                // even if a later message in the same tick does not re-zero.
+                device.recalibrated |= recalibrated_this_message;
+                if recalibrated_this_message {
+                    // Fires at most once per `Data` message, whichever of its
+                    // 3 sub-frames re-zeroed the bias.
+                    send_rumble(command_tx, data.serial_number.clone(), RumblePattern::Recalibrated);
+                }
+
+                let bias = device.gyro_bias;
+                let debiased_data = calibrated_data.map(|frame| JoyconAxisData {
+                    gyro_x: frame.gyro_x - bias.x,
+                    gyro_y: frame.gyro_y - bias.y,
+                    gyro_z: frame.gyro_z - bias.z,
+                    ..frame
+                });
+                for frame in debiased_data {
                     device.imu.update(frame);
                 }
 
@@ -119,7 +405,7 @@ fn parse_message(
                     .send_to(&rotation_packet.to_bytes().unwrap(), address)
                     .unwrap();
 
-                let acc = calc_acceleration(device.imu.rotation, &data.imu_data[2], rad_rotation);
+                let acc = calc_acceleration(device.imu.rotation, &debiased_data[2], rad_rotation);
                 if std::env::args().any(|a| &a == "debug") {
                     println!("x: {:.3}, y: {:.3}, z: {:.3}", acc.x, acc.y, acc.z);
                 }
@@ -132,6 +418,18 @@ fn parse_message(
                 socket
                     .send_to(&acceleration_packet.to_bytes().unwrap(), address)
                     .unwrap();
+
+                if now.duration_since(device.last_battery_send) >= BATTERY_SEND_INTERVAL {
+                    device.last_battery_send = now;
+                    let battery_packet = PacketType::Battery {
+                        packet_id: 0,
+                        voltage: 0.0,
+                        percentage: device.battery_percentage,
+                    };
+                    socket
+                        .send_to(&battery_packet.to_bytes().unwrap(), address)
+                        .unwrap();
+                }
             }
         }
     }
@@ -189,6 +487,7 @@ pub fn main_thread(
     receive: mpsc::Receiver<ChannelInfo>,
     output_tx: mpsc::Sender<Vec<JoyconStatus>>,
     settings: settings::Handler,
+    command_tx: mpsc::Sender<JoyconCommand>,
 ) {
     let mut devices: HashMap<String, Device> = HashMap::new();
 
@@ -198,14 +497,26 @@ pub fn main_thread(
     ];
     let socket = UdpSocket::bind(&addrs[..]).unwrap();
     socket.set_nonblocking(true).ok();
-    let address = {
-        settings
-            .load()
-            .address
-            .clone()
-            .parse::<SocketAddr>()
-            .unwrap_or_else(|_| "127.0.0.1:6969".parse().unwrap())
-    };
+    let manual_address = settings
+        .load()
+        .address
+        .clone()
+        .parse::<SocketAddr>()
+        .ok();
+    // With mDNS discovery on and no manual address configured, we start out
+    // undiscovered and broadcast probes until a server responds.
+    let discovery_enabled = settings.load().mdns_discovery && manual_address.is_none();
+    let mut address = manual_address.unwrap_or_else(|| {
+        if discovery_enabled {
+            discovery_broadcast_addr()
+        } else {
+            "127.0.0.1:6969".parse().unwrap()
+        }
+    });
+    let mut discovered = !discovery_enabled;
+    if discovery_enabled {
+        socket.set_broadcast(true).ok();
+    }
 
     let mut connected = false;
     let mut last_handshake = Instant::now() - Duration::from_secs(60);
@@ -217,39 +528,70 @@ pub fn main_thread(
         if !connected && last_handshake.elapsed().as_secs() >= 3 {
             last_handshake = Instant::now();
             slime_handshake(&socket, &address);
-            for device in devices.values().sorted_by_key(|d| d.id) {
-                device.handshake(&socket, &address);
+            if discovered {
+                for device in devices.values().sorted_by_key(|d| d.id) {
+                    device.handshake(&socket, &address);
+                }
             }
         }
-        while let Ok(len) = socket.recv(&mut buf) {
+        while let Ok((len, from_addr)) = socket.recv_from(&mut buf) {
             connected = true;
+            // Only adopt `from_addr` once we've confirmed it's actually a
+            // SlimeVR server replying, not just any stray packet landing on
+            // our broadcast-enabled socket.
             if let Ok((_, PacketType::Ping { id: _ })) = PacketType::from_bytes((&buf, 0)) {
+                if discovery_enabled && !discovered {
+                    address = from_addr;
+                    discovered = true;
+                }
                 last_ping = Instant::now();
                 socket.send_to(&buf[0..len], address).unwrap();
             }
         }
         if connected && last_ping.elapsed().as_secs() >= 3 {
             connected = false;
+            if discovery_enabled {
+                // The server went quiet; forget it and resume broadcasting
+                // so we pick up whichever instance answers next.
+                discovered = false;
+                address = discovery_broadcast_addr();
+            }
         }
 
         let mut received_message = false;
         for msg in receive.try_iter() {
             received_message = true;
-            parse_message(msg, &mut devices, &socket, &address, &settings);
+            parse_message(msg, &mut devices, &socket, &address, &settings, &command_tx);
         }
 
-        if received_message {
+        let mut newly_stale = false;
+        let now = Instant::now();
+        for device in devices.values_mut() {
+            if !device.stale && now.duration_since(device.last_seen) >= settings.device_timeout {
+                device.stale = true;
+                newly_stale = true;
+                device.send_sensor_status(&socket, &address, 0);
+            }
+        }
+
+        if received_message || newly_stale {
             let mut statuses = Vec::new();
             for (serial_number, device) in &devices {
                 statuses.push(JoyconStatus {
-                    connected: true,
+                    connected: !device.stale,
                     rotation: device.imu.euler_angles_deg(),
                     design: device.design.clone(),
                     mount_rotation: settings.joycon_rotation_get(serial_number),
                     serial_number: serial_number.clone(),
+                    recalibrated: device.recalibrated,
+                    battery_percentage: device.battery_percentage,
+                    battery_charging: device.battery_charging,
                 });
             }
             let _drop = output_tx.send(statuses);
+            for device in devices.values_mut() {
+                device.recalibrated = false;
+            }
         } else {
             thread::sleep(Duration::from_micros(500));
         }
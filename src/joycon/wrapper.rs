@@ -2,32 +2,52 @@ use std::{env, sync::mpsc};
 
 use crate::settings;
 
-use super::{main_thread, spawn_thread, test_integration::test_controllers, JoyconStatus};
+use super::{
+    main_thread,
+    spawn_thread,
+    test_integration::test_controllers,
+    JoyconCommand,
+    JoyconStatus,
+    RumblePattern,
+};
 
-fn startup(settings: settings::Handler) -> mpsc::Receiver<Vec<JoyconStatus>> {
+fn startup(
+    settings: settings::Handler,
+) -> (mpsc::Receiver<Vec<JoyconStatus>>, mpsc::Sender<JoyconCommand>) {
     let (out_tx, out_rx) = mpsc::channel();
     let (tx, rx) = mpsc::channel();
+    let (cmd_tx, cmd_rx) = mpsc::channel();
     let settings_clone = settings.clone();
-    let _drop = std::thread::spawn(move || main_thread(rx, out_tx, settings));
+    let cmd_tx_clone = cmd_tx.clone();
+    let _drop = std::thread::spawn(move || main_thread(rx, out_tx, settings, cmd_tx_clone));
 
     let tx_clone = tx.clone();
     if env::args().any(|a| &a == "test") {
         std::thread::spawn(move || test_controllers(tx_clone));
     }
-    std::thread::spawn(move || spawn_thread(tx, settings_clone));
-    out_rx
+    std::thread::spawn(move || spawn_thread(tx, settings_clone, cmd_rx));
+    (out_rx, cmd_tx)
 }
 
 pub struct JoyconIntegration {
     rx: mpsc::Receiver<Vec<JoyconStatus>>,
+    command_tx: mpsc::Sender<JoyconCommand>,
 }
 impl JoyconIntegration {
     pub fn new(settings: settings::Handler) -> Self {
-        Self {
-            rx: startup(settings),
-        }
+        let (rx, command_tx) = startup(settings);
+        Self { rx, command_tx }
     }
     pub fn poll(&self) -> Option<Vec<JoyconStatus>> {
         self.rx.try_iter().last()
     }
+
+    /// Triggers rumble on the Joy-Con with the given serial number, e.g. so
+    /// the user can identify which physical controller maps to a tracker.
+    pub fn rumble(&self, serial_number: String, pattern: RumblePattern) {
+        let _drop = self.command_tx.send(JoyconCommand::Rumble {
+            serial_number,
+            pattern,
+        });
+    }
 }